@@ -0,0 +1,333 @@
+use crate::regex::parser::{Anchor, Ast, ClassItem};
+
+/// A character class attached to a `ClassMatch` state.
+#[derive(Debug)]
+pub struct ClassMatcher {
+    pub negated: bool,
+    pub items: Vec<ClassItem>,
+}
+
+impl ClassMatcher {
+    pub fn matches(&self, c: char) -> bool {
+        let hit = self.items.iter().any(|item| match item {
+            ClassItem::Char(x) => *x == c,
+            ClassItem::Range(lo, hi) => *lo <= c && c <= *hi,
+        });
+        hit != self.negated
+    }
+}
+
+/// One instruction of the compiled Thompson NFA. `next` fields are indices
+/// into the owning `Nfa`'s `states` vector.
+#[derive(Debug)]
+pub enum State {
+    Char(char, usize),
+    Any(usize),
+    Split(usize, usize),
+    ClassMatch(ClassMatcher, usize),
+    Match,
+}
+
+/// A compiled pattern: a flat NFA plus whether the original AST anchored
+/// matching to the start and/or end of the input.
+#[derive(Debug)]
+pub struct Nfa {
+    pub states: Vec<State>,
+    pub start: usize,
+    pub anchored_start: bool,
+    pub anchored_end: bool,
+}
+
+/// An in-progress fragment of the NFA: an entry point and the list of
+/// dangling `next` pointers still waiting to be patched to whatever comes
+/// after the fragment.
+struct Fragment {
+    start: usize,
+    outs: Vec<Out>,
+}
+
+/// A dangling out-pointer identifying which field of which state to patch.
+enum Out {
+    Next(usize),
+    SplitFirst(usize),
+    SplitSecond(usize),
+}
+
+struct Compiler {
+    states: Vec<State>,
+    anchored_start: bool,
+    anchored_end: bool,
+}
+
+impl Compiler {
+    fn push(&mut self, state: State) -> usize {
+        self.states.push(state);
+        self.states.len() - 1
+    }
+
+    fn patch(&mut self, outs: &[Out], target: usize) {
+        for out in outs {
+            match out {
+                Out::Next(i) => match &mut self.states[*i] {
+                    State::Char(_, next) => *next = target,
+                    State::Any(next) => *next = target,
+                    State::ClassMatch(_, next) => *next = target,
+                    _ => unreachable!("Out::Next must point at a single-exit state"),
+                },
+                Out::SplitFirst(i) => match &mut self.states[*i] {
+                    State::Split(first, _) => *first = target,
+                    _ => unreachable!("Out::SplitFirst must point at a Split state"),
+                },
+                Out::SplitSecond(i) => match &mut self.states[*i] {
+                    State::Split(_, second) => *second = target,
+                    _ => unreachable!("Out::SplitSecond must point at a Split state"),
+                },
+            }
+        }
+    }
+
+    fn compile(&mut self, ast: &Ast) -> Fragment {
+        match ast {
+            Ast::Literal(c) => {
+                let idx = self.push(State::Char(*c, usize::MAX));
+                Fragment {
+                    start: idx,
+                    outs: vec![Out::Next(idx)],
+                }
+            }
+            Ast::AnyChar => {
+                let idx = self.push(State::Any(usize::MAX));
+                Fragment {
+                    start: idx,
+                    outs: vec![Out::Next(idx)],
+                }
+            }
+            Ast::Class { negated, items } => {
+                let matcher = ClassMatcher {
+                    negated: *negated,
+                    items: items.clone(),
+                };
+                let idx = self.push(State::ClassMatch(matcher, usize::MAX));
+                Fragment {
+                    start: idx,
+                    outs: vec![Out::Next(idx)],
+                }
+            }
+            Ast::Anchor(anchor) => {
+                match anchor {
+                    Anchor::Start => self.anchored_start = true,
+                    Anchor::End => self.anchored_end = true,
+                }
+                self.nop()
+            }
+            Ast::Group(inner) => self.compile(inner),
+            Ast::Concat(nodes) => {
+                let mut iter = nodes.iter();
+                let first = iter.next().expect("Concat must not be empty");
+                let mut frag = self.compile(first);
+                for node in iter {
+                    let next = self.compile(node);
+                    self.patch(&frag.outs, next.start);
+                    frag = Fragment {
+                        start: frag.start,
+                        outs: next.outs,
+                    };
+                }
+                frag
+            }
+            Ast::Alternate(branches) => {
+                let mut iter = branches.iter();
+                let first = iter.next().expect("Alternate must not be empty");
+                let mut frag = self.compile(first);
+                for branch in iter {
+                    let next = self.compile(branch);
+                    let split = self.push(State::Split(frag.start, next.start));
+                    let mut outs = frag.outs;
+                    outs.extend(next.outs);
+                    frag = Fragment { start: split, outs };
+                }
+                frag
+            }
+            Ast::Repeat { node, min, max } => self.compile_repeat(node, *min, *max),
+        }
+    }
+
+    /// A zero-width epsilon fragment: a `Split` whose two branches both lead
+    /// to whatever comes next, used for anchors and empty quantifier tails.
+    fn nop(&mut self) -> Fragment {
+        let idx = self.push(State::Split(usize::MAX, usize::MAX));
+        Fragment {
+            start: idx,
+            outs: vec![Out::SplitFirst(idx), Out::SplitSecond(idx)],
+        }
+    }
+
+    fn compile_repeat(&mut self, node: &Ast, min: usize, max: Option<usize>) -> Fragment {
+        match (min, max) {
+            (0, None) => self.compile_star(node),
+            (1, None) => self.compile_plus(node),
+            (0, Some(1)) => self.compile_optional(node),
+            _ => self.compile_bounded(node, min, max),
+        }
+    }
+
+    fn compile_star(&mut self, node: &Ast) -> Fragment {
+        let split = self.push(State::Split(usize::MAX, usize::MAX));
+        let body = self.compile(node);
+        self.patch(&[Out::SplitFirst(split)], body.start);
+        self.patch(&body.outs, split);
+        Fragment {
+            start: split,
+            outs: vec![Out::SplitSecond(split)],
+        }
+    }
+
+    fn compile_plus(&mut self, node: &Ast) -> Fragment {
+        let body = self.compile(node);
+        let split = self.push(State::Split(body.start, usize::MAX));
+        self.patch(&body.outs, split);
+        Fragment {
+            start: body.start,
+            outs: vec![Out::SplitSecond(split)],
+        }
+    }
+
+    fn compile_optional(&mut self, node: &Ast) -> Fragment {
+        let split = self.push(State::Split(usize::MAX, usize::MAX));
+        let body = self.compile(node);
+        self.patch(&[Out::SplitFirst(split)], body.start);
+        let mut outs = body.outs;
+        outs.push(Out::SplitSecond(split));
+        Fragment { start: split, outs }
+    }
+
+    /// Compiles `node{min,max}` as `min` required copies followed by
+    /// `max - min` nested-optional copies (or a trailing star when `max` is
+    /// unbounded), so skipping an earlier optional copy skips every copy
+    /// after it.
+    fn compile_bounded(&mut self, node: &Ast, min: usize, max: Option<usize>) -> Fragment {
+        let mut required: Option<Fragment> = None;
+        for _ in 0..min {
+            let next = self.compile(node);
+            required = Some(match required {
+                None => next,
+                Some(prev) => {
+                    self.patch(&prev.outs, next.start);
+                    Fragment {
+                        start: prev.start,
+                        outs: next.outs,
+                    }
+                }
+            });
+        }
+
+        let tail = match max {
+            None => self.compile_star(node),
+            Some(max) => self.compile_optional_chain(node, max.saturating_sub(min)),
+        };
+
+        match required {
+            None => tail,
+            Some(prev) => {
+                self.patch(&prev.outs, tail.start);
+                Fragment {
+                    start: prev.start,
+                    outs: tail.outs,
+                }
+            }
+        }
+    }
+
+    /// Builds `count` nested optional copies of `node`, right-to-left, so
+    /// skipping an earlier copy skips every copy after it. Iterative rather
+    /// than recursive: a recursive version blows the stack on large bounds
+    /// like `a{0,50000}`.
+    fn compile_optional_chain(&mut self, node: &Ast, count: usize) -> Fragment {
+        let mut tail = self.nop();
+        for _ in 0..count {
+            let split = self.push(State::Split(usize::MAX, usize::MAX));
+            let body = self.compile(node);
+            self.patch(&[Out::SplitFirst(split)], body.start);
+            self.patch(&body.outs, tail.start);
+            let mut outs = tail.outs;
+            outs.push(Out::SplitSecond(split));
+            tail = Fragment { start: split, outs };
+        }
+        tail
+    }
+}
+
+/// Compiles a regex `Ast` into a Thompson NFA.
+pub fn compile(ast: &Ast) -> Nfa {
+    let mut compiler = Compiler {
+        states: Vec::new(),
+        anchored_start: false,
+        anchored_end: false,
+    };
+    let frag = compiler.compile(ast);
+    let match_state = compiler.push(State::Match);
+    compiler.patch(&frag.outs, match_state);
+    Nfa {
+        states: compiler.states,
+        start: frag.start,
+        anchored_start: compiler.anchored_start,
+        anchored_end: compiler.anchored_end,
+    }
+}
+
+#[cfg(test)]
+use crate::regex::{lexer::lexer, parser::parser};
+
+#[cfg(test)]
+fn compile_pattern(pattern: &str) -> Nfa {
+    compile(&parser(lexer(pattern).unwrap()).unwrap())
+}
+
+#[test]
+fn test_literal_has_one_char_state_and_match() {
+    let nfa = compile_pattern("a");
+    assert!(matches!(nfa.states[nfa.start], State::Char('a', _)));
+    assert!(matches!(nfa.states.last().unwrap(), State::Match));
+}
+
+#[test]
+fn test_concat_chains_states() {
+    let nfa = compile_pattern("ab");
+    if let State::Char('a', next) = nfa.states[nfa.start] {
+        assert!(matches!(nfa.states[next], State::Char('b', _)));
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_alternate_compiles_to_split() {
+    let nfa = compile_pattern("a|b");
+    assert!(matches!(nfa.states[nfa.start], State::Split(_, _)));
+}
+
+#[test]
+fn test_star_anchored_flags_are_unset() {
+    let nfa = compile_pattern("a*");
+    assert!(!nfa.anchored_start);
+    assert!(!nfa.anchored_end);
+}
+
+#[test]
+fn test_anchors_set_flags() {
+    let nfa = compile_pattern("^a$");
+    assert!(nfa.anchored_start);
+    assert!(nfa.anchored_end);
+}
+
+#[test]
+fn test_class_compiles_to_class_match() {
+    let nfa = compile_pattern("[a-z]");
+    assert!(matches!(nfa.states[nfa.start], State::ClassMatch(_, _)));
+}
+
+#[test]
+fn test_bounded_repeat_with_large_gap_does_not_overflow_stack() {
+    let nfa = compile_pattern("a{0,50000}");
+    assert!(matches!(nfa.states[nfa.start], State::Split(_, _)));
+}
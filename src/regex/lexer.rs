@@ -1,45 +1,82 @@
-use std::{error::Error, fmt::Display};
+use std::{error::Error, fmt::Display, iter::Peekable, str::Chars};
 
 #[derive(Debug)]
-struct LexerError(String);
+pub enum LexerError {
+    UnexpectedChar { ch: char, pos: usize },
+    MalformedQuantifier { pos: usize },
+    DanglingEscape { pos: usize },
+    UnterminatedCurly { pos: usize },
+    MalformedEscape { pos: usize },
+}
 
 impl Display for LexerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "There occurred an error in lexer: {}", self.0)
+        match self {
+            LexerError::UnexpectedChar { ch, pos } => {
+                write!(f, "unexpected character '{}' at position {}", ch, pos)
+            }
+            LexerError::MalformedQuantifier { pos } => {
+                write!(f, "malformed quantifier at position {}", pos)
+            }
+            LexerError::DanglingEscape { pos } => {
+                write!(f, "dangling escape at position {}", pos)
+            }
+            LexerError::UnterminatedCurly { pos } => {
+                write!(f, "unterminated '{{' starting at position {}", pos)
+            }
+            LexerError::MalformedEscape { pos } => {
+                write!(f, "malformed escape sequence at position {}", pos)
+            }
+        }
     }
 }
 
 impl Error for LexerError {}
 
-#[derive(Debug)]
+/// A byte offset range into the source string that a `Token` was lexed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
 pub enum Parenthesis {
     LeftParenthesis,
     RightParenthesis,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum CurlyBrace {
     RightCurlyBrace,
     LeftCurlyBrace,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum Bracket {
     RightBracket,
     LeftBracket,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum Quantifier {
     ZeroOrMore(ZeroOrMore),
     OneOrMore,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum ZeroOrMore {
     Asterisk,
     QuestionMark,
 }
 
-#[derive(Debug)]
+/// Which built-in character class a `\d`/`\w`/`\s`-style escape stands for.
+#[derive(Debug, Clone, Copy)]
+pub enum ShorthandKind {
+    Digit,
+    Word,
+    Space,
+}
+
+#[derive(Debug, Clone, Copy)]
 pub enum Token {
     ElementToken(char),
     WildCardToken,
@@ -53,78 +90,184 @@ pub enum Token {
     OrToken,
     NotToken,
     DashToken,
+    /// `true` when the shorthand is negated (`\D`, `\W`, `\S`).
+    ClassShorthand(ShorthandKind, bool),
+}
+
+/// Lexes `s` into a token stream, tracking a running byte offset over a
+/// `Peekable` char iterator so multi-character constructs (`{n,m}`, `\xHH`,
+/// `\u{...}`) can look ahead before committing to a token.
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    pos: usize,
 }
 
-pub fn lexer(s: &str) -> Result<Vec<Token>, Box<dyn Error>> {
-    let mut tokens: Vec<Token> = Vec::new();
-    let mut i = 0;
-    let mut escape_found = false;
-    let mut chars = s.chars();
-    while let Some(c) = chars.next() {
-        if escape_found {
+impl<'a> Lexer<'a> {
+    fn new(s: &'a str) -> Self {
+        Lexer {
+            chars: s.chars().peekable(),
+            pos: 0,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn peek_char(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn lex(mut self) -> Result<Vec<(Token, Span)>, Box<dyn Error>> {
+        let mut tokens = Vec::new();
+        while let Some(c) = self.bump() {
+            let start = self.pos - c.len_utf8();
             match c {
-                't' => tokens.push(Token::ElementToken('\t')),
-                _ => tokens.push(Token::ElementToken(c)),
+                '\\' => self.lex_escape(start, &mut tokens)?,
+                '{' => self.lex_curly(start, &mut tokens)?,
+                _ => tokens.push(self.lex_simple(c, start)),
             }
-        } else if c == '\\' {
-            escape_found = true;
-            i += 1;
-            continue;
-        } else {
+        }
+        Ok(tokens)
+    }
+
+    fn lex_simple(&mut self, c: char, start: usize) -> (Token, Span) {
+        let span = Span { start, end: self.pos };
+        let token = match c {
+            '^' if start == 0 => Token::StartToken,
+            '^' => Token::NotToken,
+            '$' => Token::EndToken,
+            '.' => Token::WildCardToken,
+            '*' => Token::Quantifier(Quantifier::ZeroOrMore(ZeroOrMore::Asterisk)),
+            '?' => Token::Quantifier(Quantifier::ZeroOrMore(ZeroOrMore::QuestionMark)),
+            '+' => Token::Quantifier(Quantifier::OneOrMore),
+            '|' => Token::OrToken,
+            '(' => Token::Parenthesis(Parenthesis::LeftParenthesis),
+            ')' => Token::Parenthesis(Parenthesis::RightParenthesis),
+            '-' => Token::DashToken,
+            '[' => Token::Bracket(Bracket::LeftBracket),
+            ']' => Token::Bracket(Bracket::RightBracket),
+            '}' => Token::CurlyBrace(CurlyBrace::RightCurlyBrace),
+            _ => Token::ElementToken(c),
+        };
+        (token, span)
+    }
+
+    /// Lexes the body of a `{...}` quantifier, pushing the bracketing
+    /// `CurlyBrace` tokens and everything in between directly onto `tokens`.
+    fn lex_curly(&mut self, start: usize, tokens: &mut Vec<(Token, Span)>) -> Result<(), Box<dyn Error>> {
+        tokens.push((
+            Token::CurlyBrace(CurlyBrace::LeftCurlyBrace),
+            Span {
+                start,
+                end: start + 1,
+            },
+        ));
+        loop {
+            let c = match self.bump() {
+                Some(c) => c,
+                None => return Err(Box::new(LexerError::UnterminatedCurly { pos: start })),
+            };
+            let item_start = self.pos - c.len_utf8();
+            let span = Span {
+                start: item_start,
+                end: self.pos,
+            };
             match c {
-                '^' => {
-                    if i == 0 {
-                        tokens.push(Token::StartToken)
-                    } else {
-                        tokens.push(Token::NotToken)
-                    }
+                ',' => tokens.push((Token::CommaToken, span)),
+                '}' => {
+                    tokens.push((Token::CurlyBrace(CurlyBrace::RightCurlyBrace), span));
+                    return Ok(());
                 }
-                '$' => tokens.push(Token::EndToken),
-                '.' => tokens.push(Token::WildCardToken),
-                '*' => tokens.push(Token::Quantifier(Quantifier::ZeroOrMore(
-                    ZeroOrMore::Asterisk,
-                ))),
-                '?' => tokens.push(Token::Quantifier(Quantifier::ZeroOrMore(
-                    ZeroOrMore::QuestionMark,
-                ))),
-                '+' => tokens.push(Token::Quantifier(Quantifier::OneOrMore)),
-                '|' => tokens.push(Token::OrToken),
-                '(' => tokens.push(Token::Parenthesis(Parenthesis::LeftParenthesis)),
-                ')' => tokens.push(Token::Parenthesis(Parenthesis::RightParenthesis)),
-                '-' => tokens.push(Token::DashToken),
-                '[' => tokens.push(Token::Bracket(Bracket::LeftBracket)),
-                ']' => tokens.push(Token::Bracket(Bracket::RightBracket)),
-                '{' => {
-                    tokens.push(Token::CurlyBrace(CurlyBrace::LeftCurlyBrace));
-                    i += 1;
-                    while let Some(c) = chars.next() {
-                        if c == ',' {
-                            tokens.push(Token::CommaToken);
-                        } else if c.is_digit(10) {
-                            tokens.push(Token::ElementToken(c));
-                        } else if c == '}' {
-                            tokens.push(Token::CurlyBrace(CurlyBrace::RightCurlyBrace));
-                            break;
-                        } else {
-                            return Err(Box::new(LexerError("".into())));
-                        }
-                    }
+                c if c.is_ascii_digit() => tokens.push((Token::ElementToken(c), span)),
+                _ => return Err(Box::new(LexerError::MalformedQuantifier { pos: item_start })),
+            }
+        }
+    }
+
+    fn lex_escape(&mut self, start: usize, tokens: &mut Vec<(Token, Span)>) -> Result<(), Box<dyn Error>> {
+        let c = self
+            .bump()
+            .ok_or(LexerError::DanglingEscape { pos: start })?;
+        let token = match c {
+            't' => Token::ElementToken('\t'),
+            'n' => Token::ElementToken('\n'),
+            'r' => Token::ElementToken('\r'),
+            '0' => Token::ElementToken('\0'),
+            'f' => Token::ElementToken('\u{000C}'),
+            'v' => Token::ElementToken('\u{000B}'),
+            'd' => Token::ClassShorthand(ShorthandKind::Digit, false),
+            'D' => Token::ClassShorthand(ShorthandKind::Digit, true),
+            'w' => Token::ClassShorthand(ShorthandKind::Word, false),
+            'W' => Token::ClassShorthand(ShorthandKind::Word, true),
+            's' => Token::ClassShorthand(ShorthandKind::Space, false),
+            'S' => Token::ClassShorthand(ShorthandKind::Space, true),
+            'x' => Token::ElementToken(self.lex_hex_digits(start, 2)?),
+            'u' => Token::ElementToken(self.lex_unicode_escape(start)?),
+            _ => Token::ElementToken(c),
+        };
+        tokens.push((
+            token,
+            Span {
+                start,
+                end: self.pos,
+            },
+        ));
+        Ok(())
+    }
+
+    /// Reads exactly `count` hex digits (as used by `\xHH`).
+    fn lex_hex_digits(&mut self, escape_start: usize, count: usize) -> Result<char, Box<dyn Error>> {
+        let mut digits = String::new();
+        for _ in 0..count {
+            match self.bump() {
+                Some(c) if c.is_ascii_hexdigit() => digits.push(c),
+                _ => return Err(Box::new(LexerError::MalformedEscape { pos: escape_start })),
+            }
+        }
+        decode_hex(&digits, escape_start)
+    }
+
+    /// Reads a `\uHHHH` or `\u{HHHH}` escape, peeking ahead to tell the two
+    /// forms apart before consuming anything.
+    fn lex_unicode_escape(&mut self, escape_start: usize) -> Result<char, Box<dyn Error>> {
+        if self.peek_char() == Some('{') {
+            self.bump();
+            let mut digits = String::new();
+            loop {
+                match self.bump() {
+                    Some('}') => break,
+                    Some(c) if c.is_ascii_hexdigit() => digits.push(c),
+                    _ => return Err(Box::new(LexerError::MalformedEscape { pos: escape_start })),
                 }
-                '}' => tokens.push(Token::CurlyBrace(CurlyBrace::RightCurlyBrace)),
-                _ => tokens.push(Token::ElementToken(c)),
             }
+            decode_hex(&digits, escape_start)
+        } else {
+            self.lex_hex_digits(escape_start, 4)
         }
-        i += 1;
-        escape_found = false;
     }
+}
 
-    Ok(tokens)
+fn decode_hex(digits: &str, escape_start: usize) -> Result<char, Box<dyn Error>> {
+    if digits.is_empty() {
+        return Err(Box::new(LexerError::MalformedEscape { pos: escape_start }));
+    }
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| Box::new(LexerError::MalformedEscape { pos: escape_start }) as Box<dyn Error>)
+}
+
+pub fn lexer(s: &str) -> Result<Vec<(Token, Span)>, Box<dyn Error>> {
+    Lexer::new(s).lex()
 }
 
 #[test]
 fn test_simple() {
     let tokens = lexer("a").unwrap();
-    if let Token::ElementToken(c) = tokens.get(0).unwrap() {
+    if let Token::ElementToken(c) = &tokens.get(0).unwrap().0 {
         assert_eq!(*c, 'a');
     } else {
         assert!(false);
@@ -135,13 +278,13 @@ fn test_simple() {
 fn test_escaping_char() {
     let tokens = lexer("a\\a").unwrap();
     assert_eq!(tokens.len(), 2);
-    if let Token::ElementToken(c) = tokens.get(0).unwrap() {
+    if let Token::ElementToken(c) = &tokens.get(0).unwrap().0 {
         assert_eq!(*c, 'a');
     } else {
         assert!(false);
     }
 
-    if let Token::ElementToken(c) = tokens.get(1).unwrap() {
+    if let Token::ElementToken(c) = &tokens.get(1).unwrap().0 {
         assert_eq!(*c, 'a');
     } else {
         assert!(false);
@@ -151,7 +294,7 @@ fn test_escaping_char() {
 #[test]
 fn test_escaped_tab() {
     let tokens = lexer("\t").unwrap();
-    if let Token::ElementToken(c) = tokens.get(0).unwrap() {
+    if let Token::ElementToken(c) = &tokens.get(0).unwrap().0 {
         assert_eq!(*c, '\t');
     } else {
         assert!(false);
@@ -161,7 +304,7 @@ fn test_escaped_tab() {
 #[test]
 fn test_escape_wildcard() {
     let tokens = lexer("\\.").unwrap();
-    if let Token::ElementToken(c) = tokens.get(0).unwrap() {
+    if let Token::ElementToken(c) = &tokens.get(0).unwrap().0 {
         assert_eq!(*c, '.');
     } else {
         assert!(false);
@@ -171,7 +314,7 @@ fn test_escape_wildcard() {
 #[test]
 fn test_comma() {
     let tokens = lexer("a{3,5}").unwrap();
-    if let Token::CommaToken = tokens.get(3).unwrap() {
+    if let Token::CommaToken = &tokens.get(3).unwrap().0 {
         assert!(true);
     } else {
         assert!(false);
@@ -181,7 +324,7 @@ fn test_comma() {
 #[test]
 fn test_comma_is_element() {
     let tokens = lexer("a,").unwrap();
-    if let Token::ElementToken(c) = tokens.get(1).unwrap() {
+    if let Token::ElementToken(c) = &tokens.get(1).unwrap().0 {
         assert_eq!(*c, ',');
     } else {
         assert!(false);
@@ -191,7 +334,7 @@ fn test_comma_is_element() {
 #[test]
 fn test_match_start() {
     let tokens = lexer("^a").unwrap();
-    if let Token::StartToken = tokens.get(0).unwrap() {
+    if let Token::StartToken = &tokens.get(0).unwrap().0 {
         assert!(true);
     } else {
         assert!(false);
@@ -201,7 +344,7 @@ fn test_match_start() {
 #[test]
 fn test_match_end() {
     let tokens = lexer("a$").unwrap();
-    if let Token::EndToken = tokens.iter().last().unwrap() {
+    if let Token::EndToken = &tokens.iter().last().unwrap().0 {
         assert!(true);
     } else {
         assert!(false);
@@ -216,3 +359,142 @@ fn test_fail_curly() {
         assert!(false);
     }
 }
+
+#[test]
+fn test_malformed_quantifier() {
+    let err = lexer("a{3,x}").unwrap_err();
+    match err.downcast_ref::<LexerError>().unwrap() {
+        LexerError::MalformedQuantifier { pos } => assert_eq!(*pos, 4),
+        other => panic!("expected MalformedQuantifier, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_unterminated_curly() {
+    let err = lexer("a{3,5").unwrap_err();
+    match err.downcast_ref::<LexerError>().unwrap() {
+        LexerError::UnterminatedCurly { pos } => assert_eq!(*pos, 1),
+        other => panic!("expected UnterminatedCurly, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_dangling_escape() {
+    let err = lexer("a\\").unwrap_err();
+    match err.downcast_ref::<LexerError>().unwrap() {
+        LexerError::DanglingEscape { pos } => assert_eq!(*pos, 1),
+        other => panic!("expected DanglingEscape, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_span_simple() {
+    let tokens = lexer("ab").unwrap();
+    assert_eq!(tokens.get(0).unwrap().1, Span { start: 0, end: 1 });
+    assert_eq!(tokens.get(1).unwrap().1, Span { start: 1, end: 2 });
+}
+
+#[test]
+fn test_span_escape() {
+    let tokens = lexer("a\\t").unwrap();
+    assert_eq!(tokens.get(1).unwrap().1, Span { start: 1, end: 3 });
+}
+
+#[test]
+fn test_span_curly_quantifier() {
+    let tokens = lexer("a{3,5}").unwrap();
+    assert_eq!(tokens.get(0).unwrap().1, Span { start: 0, end: 1 });
+    assert_eq!(tokens.get(1).unwrap().1, Span { start: 1, end: 2 });
+    assert_eq!(tokens.get(5).unwrap().1, Span { start: 5, end: 6 });
+}
+
+#[test]
+fn test_escape_newline_and_friends() {
+    let tokens = lexer("\\n\\r\\0\\f\\v").unwrap();
+    let expected = ['\n', '\r', '\0', '\u{000C}', '\u{000B}'];
+    for (token, want) in tokens.iter().zip(expected.iter()) {
+        if let Token::ElementToken(c) = token.0 {
+            assert_eq!(c, *want);
+        } else {
+            assert!(false);
+        }
+    }
+}
+
+#[test]
+fn test_escape_hex() {
+    let tokens = lexer("\\x41").unwrap();
+    assert_eq!(tokens.len(), 1);
+    if let Token::ElementToken(c) = tokens.get(0).unwrap().0 {
+        assert_eq!(c, 'A');
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_escape_hex_malformed() {
+    let err = lexer("\\x4").unwrap_err();
+    match err.downcast_ref::<LexerError>().unwrap() {
+        LexerError::MalformedEscape { pos } => assert_eq!(*pos, 0),
+        other => panic!("expected MalformedEscape, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_escape_unicode_fixed_width() {
+    let tokens = lexer("\\u0041").unwrap();
+    assert_eq!(tokens.len(), 1);
+    if let Token::ElementToken(c) = tokens.get(0).unwrap().0 {
+        assert_eq!(c, 'A');
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_escape_unicode_braced() {
+    let tokens = lexer("\\u{1F600}").unwrap();
+    assert_eq!(tokens.len(), 1);
+    if let Token::ElementToken(c) = tokens.get(0).unwrap().0 {
+        assert_eq!(c, '\u{1F600}');
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_class_shorthand_digit() {
+    let tokens = lexer("\\d").unwrap();
+    if let Token::ClassShorthand(ShorthandKind::Digit, negated) = tokens.get(0).unwrap().0 {
+        assert!(!negated);
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_class_shorthand_negated() {
+    let tokens = lexer("\\D\\W\\S").unwrap();
+    for token in tokens.iter() {
+        if let Token::ClassShorthand(_, negated) = token.0 {
+            assert!(negated);
+        } else {
+            assert!(false);
+        }
+    }
+}
+
+#[test]
+fn test_lex_unicode_escape_braced_in_isolation() {
+    let mut lex = Lexer::new("{1F600}a");
+    assert_eq!(lex.lex_unicode_escape(0).unwrap(), '\u{1F600}');
+    assert_eq!(lex.bump(), Some('a'));
+}
+
+#[test]
+fn test_lex_hex_digits_in_isolation() {
+    let mut lex = Lexer::new("41zz");
+    assert_eq!(lex.lex_hex_digits(0, 2).unwrap(), 'A');
+    assert_eq!(lex.bump(), Some('z'));
+}
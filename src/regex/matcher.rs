@@ -0,0 +1,207 @@
+use std::collections::BTreeSet;
+
+use crate::regex::compile::{Nfa, State};
+
+/// Runs the classic Thompson multi-state simulation over a compiled `Nfa`.
+pub struct Matcher<'a> {
+    nfa: &'a Nfa,
+}
+
+impl<'a> Matcher<'a> {
+    pub fn new(nfa: &'a Nfa) -> Self {
+        Matcher { nfa }
+    }
+
+    pub fn is_match(&self, input: &str) -> bool {
+        let chars: Vec<char> = input.chars().collect();
+        if self.nfa.anchored_start {
+            self.run_from(&chars, 0)
+        } else {
+            (0..=chars.len()).any(|start| self.run_from(&chars, start))
+        }
+    }
+
+    /// Follows `Split` epsilon transitions into `set`, stopping at states
+    /// that consume a character (or `Match`). `set` also doubles as the
+    /// visited-set so star loops can't recurse forever.
+    fn closure(&self, state: usize, set: &mut BTreeSet<usize>) {
+        if !set.insert(state) {
+            return;
+        }
+        if let State::Split(first, second) = self.nfa.states[state] {
+            self.closure(first, set);
+            self.closure(second, set);
+        }
+    }
+
+    fn has_match(&self, states: &BTreeSet<usize>) -> bool {
+        states
+            .iter()
+            .any(|&s| matches!(self.nfa.states[s], State::Match))
+    }
+
+    fn run_from(&self, chars: &[char], start: usize) -> bool {
+        let mut current = BTreeSet::new();
+        self.closure(self.nfa.start, &mut current);
+        if !self.nfa.anchored_end && self.has_match(&current) {
+            return true;
+        }
+
+        for &c in &chars[start..] {
+            let mut next = BTreeSet::new();
+            for &state in &current {
+                match &self.nfa.states[state] {
+                    State::Char(expected, target) if *expected == c => {
+                        self.closure(*target, &mut next)
+                    }
+                    State::Any(target) => self.closure(*target, &mut next),
+                    State::ClassMatch(class, target) if class.matches(c) => {
+                        self.closure(*target, &mut next)
+                    }
+                    _ => {}
+                }
+            }
+            current = next;
+            if !self.nfa.anchored_end && self.has_match(&current) {
+                return true;
+            }
+        }
+
+        self.has_match(&current)
+    }
+}
+
+/// Reports whether `input` matches the compiled pattern `nfa`.
+pub fn matcher(nfa: &Nfa, input: &str) -> bool {
+    Matcher::new(nfa).is_match(input)
+}
+
+#[cfg(test)]
+use crate::regex::{compile::compile, lexer::lexer, parser::parser};
+
+#[cfg(test)]
+fn is_match(pattern: &str, input: &str) -> bool {
+    let nfa = compile(&parser(lexer(pattern).unwrap()).unwrap());
+    matcher(&nfa, input)
+}
+
+#[test]
+fn test_literal_match() {
+    assert!(is_match("a", "a"));
+    assert!(!is_match("a", "b"));
+}
+
+#[test]
+fn test_literal_matches_as_substring() {
+    assert!(is_match("b", "abc"));
+}
+
+#[test]
+fn test_concat() {
+    assert!(is_match("abc", "abc"));
+    assert!(!is_match("abc", "abd"));
+}
+
+#[test]
+fn test_alternate() {
+    assert!(is_match("a|b", "b"));
+    assert!(!is_match("a|b", "c"));
+}
+
+#[test]
+fn test_star() {
+    assert!(is_match("a*", ""));
+    assert!(is_match("a*", "aaaa"));
+}
+
+#[test]
+fn test_plus_requires_one() {
+    assert!(is_match("a+", "a"));
+    assert!(!is_match("a+", ""));
+}
+
+#[test]
+fn test_question_mark() {
+    assert!(is_match("colou?r", "color"));
+    assert!(is_match("colou?r", "colour"));
+    assert!(!is_match("colou?r", "colouur"));
+}
+
+#[test]
+fn test_any_char() {
+    assert!(is_match("a.c", "abc"));
+    assert!(!is_match("a.c", "ac"));
+}
+
+#[test]
+fn test_curly_exact() {
+    assert!(is_match("a{3}", "aaa"));
+    assert!(!is_match("a{3}", "aa"));
+}
+
+#[test]
+fn test_curly_range() {
+    assert!(is_match("a{2,3}", "aa"));
+    assert!(is_match("a{2,3}", "aaa"));
+    assert!(!is_match("a{2,3}", "a"));
+}
+
+#[test]
+fn test_curly_open_ended() {
+    assert!(is_match("a{2,}", "aaaaa"));
+    assert!(!is_match("a{2,}", "a"));
+}
+
+#[test]
+fn test_group_with_quantifier() {
+    assert!(is_match("(ab)+", "ababab"));
+    assert!(!is_match("(ab)+", "xyz"));
+}
+
+#[test]
+fn test_class() {
+    assert!(is_match("[a-c]", "b"));
+    assert!(!is_match("[a-c]", "d"));
+}
+
+#[test]
+fn test_negated_class() {
+    assert!(is_match("[^a-c]", "d"));
+    assert!(!is_match("[^a-c]", "b"));
+}
+
+#[test]
+fn test_start_anchor() {
+    assert!(is_match("^abc", "abcdef"));
+    assert!(!is_match("^abc", "xabc"));
+}
+
+#[test]
+fn test_end_anchor() {
+    assert!(is_match("abc$", "xabc"));
+    assert!(!is_match("abc$", "abcdef"));
+}
+
+#[test]
+fn test_digit_shorthand_quantified() {
+    assert!(is_match("\\d{3}", "123"));
+    assert!(!is_match("\\d{3}", "12a"));
+}
+
+#[test]
+fn test_word_shorthand() {
+    assert!(is_match("\\w+", "hello_world"));
+    assert!(!is_match("\\w", "!"));
+}
+
+#[test]
+fn test_space_shorthand() {
+    assert!(is_match("a\\sb", "a b"));
+    assert!(!is_match("a\\sb", "axb"));
+}
+
+#[test]
+fn test_negated_digit_shorthand() {
+    assert!(is_match("\\D", "a"));
+    assert!(!is_match("\\D", "5"));
+}
@@ -0,0 +1,4 @@
+pub mod compile;
+pub mod lexer;
+pub mod matcher;
+pub mod parser;
@@ -0,0 +1,593 @@
+use std::{error::Error, fmt::Display};
+
+use crate::regex::lexer::{
+    Bracket, CurlyBrace, Parenthesis, Quantifier, ShorthandKind, Span, Token, ZeroOrMore,
+};
+
+#[derive(Debug)]
+pub enum Anchor {
+    Start,
+    End,
+}
+
+#[derive(Debug, Clone)]
+pub enum ClassItem {
+    Char(char),
+    Range(char, char),
+}
+
+#[derive(Debug)]
+pub enum Ast {
+    Literal(char),
+    AnyChar,
+    Concat(Vec<Ast>),
+    Alternate(Vec<Ast>),
+    Repeat {
+        node: Box<Ast>,
+        min: usize,
+        max: Option<usize>,
+    },
+    Group(Box<Ast>),
+    Class {
+        negated: bool,
+        items: Vec<ClassItem>,
+    },
+    Anchor(Anchor),
+}
+
+#[derive(Debug)]
+pub enum ParserError {
+    UnexpectedEndOfInput { pos: usize },
+    UnmatchedParenthesis { pos: usize },
+    QuantifierWithoutAtom { pos: usize },
+    MalformedQuantifier { pos: usize },
+    EmptyAlternationBranch { pos: usize },
+    MalformedClass { pos: usize },
+    UnexpectedToken { pos: usize },
+}
+
+impl Display for ParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParserError::UnexpectedEndOfInput { pos } => {
+                write!(f, "unexpected end of input at position {}", pos)
+            }
+            ParserError::UnmatchedParenthesis { pos } => {
+                write!(f, "unmatched parenthesis at position {}", pos)
+            }
+            ParserError::QuantifierWithoutAtom { pos } => {
+                write!(f, "quantifier with no preceding atom at position {}", pos)
+            }
+            ParserError::MalformedQuantifier { pos } => {
+                write!(f, "malformed quantifier bounds at position {}", pos)
+            }
+            ParserError::EmptyAlternationBranch { pos } => {
+                write!(f, "empty alternation branch at position {}", pos)
+            }
+            ParserError::MalformedClass { pos } => {
+                write!(f, "malformed character class at position {}", pos)
+            }
+            ParserError::UnexpectedToken { pos } => {
+                write!(f, "unexpected token at position {}", pos)
+            }
+        }
+    }
+}
+
+impl Error for ParserError {}
+
+/// Recursive-descent parser that turns a lexed token stream into a regex `Ast`.
+///
+/// Grammar, loosest to tightest binding: alternation (`|`), concatenation
+/// (sequence of atoms), postfix quantifiers (`*`, `+`, `?`, `{n,m}`).
+pub struct Parser {
+    tokens: Vec<(Token, Span)>,
+    pos: usize,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<(Token, Span)>) -> Self {
+        Parser { tokens, pos: 0 }
+    }
+
+    pub fn parse(&mut self) -> Result<Ast, Box<dyn Error>> {
+        let ast = self.parse_alternation()?;
+        if let Some((_, span)) = self.peek() {
+            return Err(Box::new(ParserError::UnmatchedParenthesis { pos: span.start }));
+        }
+        Ok(ast)
+    }
+
+    fn peek(&self) -> Option<(Token, Span)> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn advance(&mut self) -> Option<(Token, Span)> {
+        let token = self.tokens.get(self.pos).copied();
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn end_pos(&self) -> usize {
+        self.tokens.last().map(|(_, span)| span.end).unwrap_or(0)
+    }
+
+    fn parse_alternation(&mut self) -> Result<Ast, Box<dyn Error>> {
+        let mut branches = vec![self.parse_concat()?];
+        while let Some((Token::OrToken, _)) = self.peek() {
+            self.advance();
+            branches.push(self.parse_concat()?);
+        }
+        if branches.len() == 1 {
+            Ok(branches.pop().unwrap())
+        } else {
+            Ok(Ast::Alternate(branches))
+        }
+    }
+
+    fn parse_concat(&mut self) -> Result<Ast, Box<dyn Error>> {
+        let start_pos = self.peek().map(|(_, span)| span.start).unwrap_or(self.end_pos());
+        let mut nodes = Vec::new();
+        while let Some((token, _)) = self.peek() {
+            match token {
+                Token::OrToken | Token::Parenthesis(Parenthesis::RightParenthesis) => break,
+                _ => nodes.push(self.parse_quantified()?),
+            }
+        }
+        if nodes.is_empty() {
+            return Err(Box::new(ParserError::EmptyAlternationBranch { pos: start_pos }));
+        }
+        if nodes.len() == 1 {
+            Ok(nodes.pop().unwrap())
+        } else {
+            Ok(Ast::Concat(nodes))
+        }
+    }
+
+    fn parse_quantified(&mut self) -> Result<Ast, Box<dyn Error>> {
+        let atom = self.parse_atom()?;
+        match self.peek() {
+            Some((Token::Quantifier(Quantifier::ZeroOrMore(ZeroOrMore::Asterisk)), _)) => {
+                self.advance();
+                Ok(Ast::Repeat {
+                    node: Box::new(atom),
+                    min: 0,
+                    max: None,
+                })
+            }
+            Some((Token::Quantifier(Quantifier::ZeroOrMore(ZeroOrMore::QuestionMark)), _)) => {
+                self.advance();
+                Ok(Ast::Repeat {
+                    node: Box::new(atom),
+                    min: 0,
+                    max: Some(1),
+                })
+            }
+            Some((Token::Quantifier(Quantifier::OneOrMore), _)) => {
+                self.advance();
+                Ok(Ast::Repeat {
+                    node: Box::new(atom),
+                    min: 1,
+                    max: None,
+                })
+            }
+            Some((Token::CurlyBrace(CurlyBrace::LeftCurlyBrace), open_span)) => {
+                self.advance();
+                self.parse_curly_quantifier(atom, open_span)
+            }
+            _ => Ok(atom),
+        }
+    }
+
+    fn parse_curly_quantifier(&mut self, atom: Ast, open_span: Span) -> Result<Ast, Box<dyn Error>> {
+        let min = self.parse_curly_number(open_span)?;
+        match self.advance() {
+            Some((Token::CurlyBrace(CurlyBrace::RightCurlyBrace), _)) => Ok(Ast::Repeat {
+                node: Box::new(atom),
+                min,
+                max: Some(min),
+            }),
+            Some((Token::CommaToken, _)) => {
+                let at_close = matches!(
+                    self.peek(),
+                    Some((Token::CurlyBrace(CurlyBrace::RightCurlyBrace), _))
+                );
+                let max = if at_close {
+                    None
+                } else {
+                    Some(self.parse_curly_number(open_span)?)
+                };
+                match self.advance() {
+                    Some((Token::CurlyBrace(CurlyBrace::RightCurlyBrace), _)) => {
+                        if max.is_some_and(|max| max < min) {
+                            return Err(Box::new(ParserError::MalformedQuantifier {
+                                pos: open_span.start,
+                            }));
+                        }
+                        Ok(Ast::Repeat {
+                            node: Box::new(atom),
+                            min,
+                            max,
+                        })
+                    }
+                    _ => Err(Box::new(ParserError::MalformedQuantifier { pos: open_span.start })),
+                }
+            }
+            _ => Err(Box::new(ParserError::MalformedQuantifier { pos: open_span.start })),
+        }
+    }
+
+    fn parse_curly_number(&mut self, open_span: Span) -> Result<usize, Box<dyn Error>> {
+        let mut digits = String::new();
+        while let Some((Token::ElementToken(c), _)) = self.peek() {
+            if c.is_ascii_digit() {
+                digits.push(c);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        digits
+            .parse()
+            .map_err(|_| Box::new(ParserError::MalformedQuantifier { pos: open_span.start }) as Box<dyn Error>)
+    }
+
+    fn parse_atom(&mut self) -> Result<Ast, Box<dyn Error>> {
+        let (token, span) = match self.advance() {
+            Some(t) => t,
+            None => return Err(Box::new(ParserError::UnexpectedEndOfInput { pos: self.end_pos() })),
+        };
+        match token {
+            Token::ElementToken(c) => Ok(Ast::Literal(c)),
+            Token::WildCardToken => Ok(Ast::AnyChar),
+            Token::StartToken => Ok(Ast::Anchor(Anchor::Start)),
+            Token::EndToken => Ok(Ast::Anchor(Anchor::End)),
+            Token::Parenthesis(Parenthesis::LeftParenthesis) => {
+                let inner = self.parse_alternation()?;
+                match self.advance() {
+                    Some((Token::Parenthesis(Parenthesis::RightParenthesis), _)) => {
+                        Ok(Ast::Group(Box::new(inner)))
+                    }
+                    _ => Err(Box::new(ParserError::UnmatchedParenthesis { pos: span.start })),
+                }
+            }
+            Token::Bracket(Bracket::LeftBracket) => self.parse_class(span),
+            Token::ClassShorthand(kind, negated) => Ok(shorthand_class(kind, negated)),
+            Token::Quantifier(_) | Token::CurlyBrace(_) => {
+                Err(Box::new(ParserError::QuantifierWithoutAtom { pos: span.start }))
+            }
+            _ => Err(Box::new(ParserError::UnexpectedToken { pos: span.start })),
+        }
+    }
+
+    /// Parses the body of a `[...]` class, stopping at the closing bracket.
+    /// Un-negated shorthand escapes (`\d`, `\w`, `\s`) are allowed inside and
+    /// their expansion is merged into the surrounding item list. Negated
+    /// shorthands (`\D`, `\W`, `\S`) can't be expressed as a finite set of
+    /// chars/ranges to merge into an enclosing class, so they're rejected as
+    /// a malformed class rather than silently doing the wrong thing.
+    fn parse_class(&mut self, open_span: Span) -> Result<Ast, Box<dyn Error>> {
+        let negated = matches!(self.peek(), Some((Token::NotToken, _)));
+        if negated {
+            self.advance();
+        }
+        let mut items = Vec::new();
+        loop {
+            match self.advance() {
+                Some((Token::Bracket(Bracket::RightBracket), _)) => break,
+                Some((Token::ClassShorthand(kind, shorthand_negated), span)) => {
+                    if shorthand_negated {
+                        return Err(Box::new(ParserError::MalformedClass { pos: span.start }));
+                    }
+                    items.extend(shorthand_items(kind));
+                }
+                Some((Token::ElementToken(c), _)) => {
+                    if matches!(self.peek(), Some((Token::DashToken, _))) {
+                        self.advance();
+                        match self.advance() {
+                            Some((Token::ElementToken(end), end_span)) => {
+                                if end < c {
+                                    return Err(Box::new(ParserError::MalformedClass {
+                                        pos: end_span.start,
+                                    }));
+                                }
+                                items.push(ClassItem::Range(c, end));
+                            }
+                            Some((_, span)) => {
+                                return Err(Box::new(ParserError::MalformedClass { pos: span.start }))
+                            }
+                            None => {
+                                return Err(Box::new(ParserError::UnexpectedEndOfInput {
+                                    pos: self.end_pos(),
+                                }))
+                            }
+                        }
+                    } else {
+                        items.push(ClassItem::Char(c));
+                    }
+                }
+                Some((_, span)) => return Err(Box::new(ParserError::MalformedClass { pos: span.start })),
+                None => {
+                    return Err(Box::new(ParserError::UnexpectedEndOfInput {
+                        pos: open_span.start,
+                    }))
+                }
+            }
+        }
+        Ok(Ast::Class { negated, items })
+    }
+}
+
+/// The chars/ranges a `\d`/`\w`/`\s`-style shorthand expands to.
+fn shorthand_items(kind: ShorthandKind) -> Vec<ClassItem> {
+    match kind {
+        ShorthandKind::Digit => vec![ClassItem::Range('0', '9')],
+        ShorthandKind::Word => vec![
+            ClassItem::Range('a', 'z'),
+            ClassItem::Range('A', 'Z'),
+            ClassItem::Range('0', '9'),
+            ClassItem::Char('_'),
+        ],
+        ShorthandKind::Space => vec![
+            ClassItem::Char(' '),
+            ClassItem::Char('\t'),
+            ClassItem::Char('\n'),
+            ClassItem::Char('\r'),
+            ClassItem::Char('\u{000C}'),
+            ClassItem::Char('\u{000B}'),
+        ],
+    }
+}
+
+/// Expands a `\d`/`\w`/`\s`-style shorthand token into the `Class` it stands
+/// for, so the rest of the pipeline only ever has to deal with `Ast::Class`.
+fn shorthand_class(kind: ShorthandKind, negated: bool) -> Ast {
+    Ast::Class { negated, items: shorthand_items(kind) }
+}
+
+/// Parses a lexed token stream into a regex `Ast`.
+pub fn parser(tokens: Vec<(Token, Span)>) -> Result<Ast, Box<dyn Error>> {
+    Parser::new(tokens).parse()
+}
+
+#[cfg(test)]
+use crate::regex::lexer::lexer;
+
+#[cfg(test)]
+fn parse(pattern: &str) -> Ast {
+    parser(lexer(pattern).unwrap()).unwrap()
+}
+
+#[test]
+fn test_literal() {
+    if let Ast::Literal(c) = parse("a") {
+        assert_eq!(c, 'a');
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_concat() {
+    if let Ast::Concat(nodes) = parse("ab") {
+        assert_eq!(nodes.len(), 2);
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_alternate() {
+    if let Ast::Alternate(branches) = parse("a|b") {
+        assert_eq!(branches.len(), 2);
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_star() {
+    if let Ast::Repeat { min, max, .. } = parse("a*") {
+        assert_eq!(min, 0);
+        assert_eq!(max, None);
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_plus() {
+    if let Ast::Repeat { min, max, .. } = parse("a+") {
+        assert_eq!(min, 1);
+        assert_eq!(max, None);
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_question_mark() {
+    if let Ast::Repeat { min, max, .. } = parse("a?") {
+        assert_eq!(min, 0);
+        assert_eq!(max, Some(1));
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_curly_exact() {
+    if let Ast::Repeat { min, max, .. } = parse("a{3}") {
+        assert_eq!(min, 3);
+        assert_eq!(max, Some(3));
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_curly_range() {
+    if let Ast::Repeat { min, max, .. } = parse("a{3,5}") {
+        assert_eq!(min, 3);
+        assert_eq!(max, Some(5));
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_curly_open_ended() {
+    if let Ast::Repeat { min, max, .. } = parse("a{3,}") {
+        assert_eq!(min, 3);
+        assert_eq!(max, None);
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_group() {
+    if let Ast::Group(inner) = parse("(a)") {
+        assert!(matches!(*inner, Ast::Literal('a')));
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_class_range() {
+    if let Ast::Class { negated, items } = parse("[a-z]") {
+        assert!(!negated);
+        assert_eq!(items.len(), 1);
+        assert!(matches!(items[0], ClassItem::Range('a', 'z')));
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_negated_class() {
+    if let Ast::Class { negated, .. } = parse("[^a]") {
+        assert!(negated);
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_anchors() {
+    if let Ast::Concat(nodes) = parse("^a$") {
+        assert!(matches!(nodes[0], Ast::Anchor(Anchor::Start)));
+        assert!(matches!(nodes[2], Ast::Anchor(Anchor::End)));
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_fail_unmatched_parenthesis() {
+    let err = parser(lexer("(a").unwrap()).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ParserError>().unwrap(),
+        ParserError::UnmatchedParenthesis { .. }
+    ));
+}
+
+#[test]
+fn test_fail_quantifier_without_atom() {
+    let err = parser(lexer("*a").unwrap()).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ParserError>().unwrap(),
+        ParserError::QuantifierWithoutAtom { .. }
+    ));
+}
+
+#[test]
+fn test_fail_empty_alternation_branch() {
+    let err = parser(lexer("a|").unwrap()).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ParserError>().unwrap(),
+        ParserError::EmptyAlternationBranch { .. }
+    ));
+}
+
+#[test]
+fn test_fail_curly_max_less_than_min() {
+    let err = parser(lexer("a{3,1}").unwrap()).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ParserError>().unwrap(),
+        ParserError::MalformedQuantifier { .. }
+    ));
+}
+
+#[test]
+fn test_digit_shorthand_becomes_class() {
+    if let Ast::Class { negated, items } = parse("\\d") {
+        assert!(!negated);
+        assert!(matches!(items[..], [ClassItem::Range('0', '9')]));
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_negated_word_shorthand_becomes_class() {
+    if let Ast::Class { negated, .. } = parse("\\W") {
+        assert!(negated);
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_fail_class_range_reversed() {
+    let err = parser(lexer("[z-a]").unwrap()).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ParserError>().unwrap(),
+        ParserError::MalformedClass { .. }
+    ));
+}
+
+#[test]
+fn test_class_with_digit_shorthand_merges_items() {
+    if let Ast::Class { negated, items } = parse("[\\da-z]") {
+        assert!(!negated);
+        assert_eq!(items.len(), 2);
+        assert!(matches!(items[0], ClassItem::Range('0', '9')));
+        assert!(matches!(items[1], ClassItem::Range('a', 'z')));
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_class_with_bare_shorthand() {
+    if let Ast::Class { negated, items } = parse("[\\d]") {
+        assert!(!negated);
+        assert!(matches!(items[..], [ClassItem::Range('0', '9')]));
+    } else {
+        assert!(false);
+    }
+}
+
+#[test]
+fn test_fail_negated_shorthand_inside_class() {
+    let err = parser(lexer("[\\D]").unwrap()).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ParserError>().unwrap(),
+        ParserError::MalformedClass { .. }
+    ));
+}
+
+#[test]
+fn test_digit_shorthand_with_quantifier() {
+    if let Ast::Repeat { node, min, max } = parse("\\d{3}") {
+        assert!(matches!(*node, Ast::Class { negated: false, .. }));
+        assert_eq!(min, 3);
+        assert_eq!(max, Some(3));
+    } else {
+        assert!(false);
+    }
+}